@@ -1,23 +1,149 @@
+//! HP-IL bus-management state machine for a single loop device, built on
+//! top of the `hpil` wire decoder.
+
+// Not yet wired into a controller/loop driver in `main`, so everything
+// below is exercised only by the tests at the bottom of this file.
+#![allow(dead_code)]
+
+use crate::hpil::{Message, MessageType};
 
 enum Command {
     Id,
     Config,
 }
 
-///
+/// Iterates the 5 nibbles of a device's `id` field, most-significant
+/// first, each wrapped as an `Identify`-class `Message` as sent in
+/// response to `Command::Id`. See `BusDevice::id`.
+struct IdResponse {
+    id: u32,
+    // nibbles remaining to emit
+    remaining: u8,
+}
+
+impl IdResponse {
+    fn new(id: u32) -> Self {
+        IdResponse { id, remaining: 5 }
+    }
+}
+
+impl Iterator for IdResponse {
+    type Item = Message;
+
+    fn next(&mut self) -> Option<Message> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+        let nibble = (self.id >> (self.remaining * 4)) & 0xf;
+        // control = 0b111: sync bit set, class bits 0b11 (Identify)
+        Some(Message::new((0b111 << 8) | nibble as u16))
+    }
+}
+
+/// A byte-addressable device mappable onto a CPU's memory bus, in the
+/// style used by small CPU-emulator crates: report how many bytes you
+/// occupy, then read/write a byte at a time by an address relative to
+/// your own base.
+pub trait AddressableDevice {
+    /// number of bytes this device occupies
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// read the byte at `addr` (relative to this device's own base), or
+    /// `None` if out of range
+    fn read(&self, addr: u16) -> Option<u8>;
+
+    /// write `value` to `addr` (relative to this device's own base).
+    /// Returns `false` if `addr` is out of range or the backing store is
+    /// read-only (e.g. `ROM`).
+    fn write(&mut self, addr: u16, value: u8) -> bool;
+}
+
+/// Concrete storage a `BusDevice` decodes its `id` nibbles into. See
+/// `BusDevice::id` and `Backing::from_id`.
+enum Backing {
+    Ram(Vec<u8>),
+    Rom(&'static [u8]),
+    /// memory-mapped-io, subtype 0: a single-byte HP-IL mailbox register
+    Mailbox(u8),
+}
+
+impl Default for Backing {
+    fn default() -> Self {
+        Backing::Ram(Vec::new())
+    }
+}
+
+impl Backing {
+    /// Decode `id`'s size/type/subtype nibbles (see `BusDevice::id`)
+    /// into the concrete backing store they describe. `rom` supplies the
+    /// image for a `ROM`-type id; `None` for an unassigned type/subtype
+    /// nibble, or a `ROM` id with no image supplied.
+    fn from_id(id: u32, rom: Option<&'static [u8]>) -> Option<Self> {
+        // nibbles, most-significant first: see `BusDevice::id`.
+        let size_nibble = (id >> 16) & 0xf;
+        let device_type = (id >> 8) & 0xf;
+
+        match device_type {
+            0 => Some(Backing::Ram(vec![0; Self::size_bytes(14, size_nibble)])),
+            1 => rom.map(Backing::Rom),
+            0xf => match (id >> 4) & 0xf {
+                0 => Some(Backing::Mailbox(0)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // `exp_base` is 14 for memory, 18 for memory-mapped-io, per the size
+    // nibble's doc comment on `BusDevice::id`.
+    fn size_bytes(exp_base: i32, size_nibble: u32) -> usize {
+        let exp = exp_base - size_nibble as i32;
+        if exp >= 0 {
+            1024usize << exp
+        } else {
+            1024usize >> (-exp)
+        }
+    }
+}
+
+#[derive(Default)]
 struct BusDevice {
     // state
     configured: bool,
 
+    // address latched from `Command::Config`, once configured
+    address: Option<u8>,
+
+    // whether this device is the active listener; cleared by
+    // `MessageType::Unlisten`
+    listener_active: bool,
+
+    // gated by the `MessageType::ReadyForCommand`/`SendDataReady`
+    // handshake
+    data_ready: bool,
+
+    // in-progress `Command::Id` reply, drained by `out_signals`
+    identify_reply: Option<IdResponse>,
+
+    // backing store decoded from `id`'s type/size nibbles; see
+    // `BusDevice::new`
+    backing: Backing,
+
     // per device
 
 
     /// 5-nibbles used (2.5 bytes)
-    /// 
+    ///
     /// sent in responce to `Command::Id` when `daisy_in` is high.
-    /// 
+    ///
     /// Nibbles:
-    /// 
+    ///
     /// 0: (14 - log2(size in KB)) if memory
     ///         9 to F allowed for RAM
     ///         7 to F allowed for other memory
@@ -44,25 +170,205 @@ struct BusSignalsOut {
 }
 
 impl BusDevice {
-    fn command(&mut self, cmd: Command, sig: BusSignalsIn)
+    /// Build a device from its 5-nibble `id`, decoding the size/type
+    /// nibbles into a concrete backing store. `rom` supplies the image
+    /// for a `ROM`-type id; ignored otherwise.
+    fn new(id: u32, rom: Option<&'static [u8]>) -> Self {
+        BusDevice {
+            id,
+            backing: Backing::from_id(id, rom).unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    /// Handle a `Command`-class frame. `data` is the frame's payload
+    /// byte (`Message::data()`) — for `Command::Config` this carries the
+    /// address being assigned during daisy-chain auto-addressing.
+    fn command(&mut self, cmd: Command, data: u8, sig: BusSignalsIn)
     {
         if !self.configured && !sig.daisy_in {
             // no action if unconfigured and no daisy_in present
             return;
         }
 
-        if !self.configured && sig.daisy_in {
-            // respond to `Id` or `Config` only
+        // reaching here, we're either configured or unconfigured with
+        // `daisy_in` asserted — respond to `Id` or `Config` only, per
+        // `BusSignalsIn::daisy_in`
+        match cmd {
+            Command::Config => {
+                if !self.configured {
+                    self.address = Some(data);
+                    self.configured = true;
+                }
+            }
+            Command::Id => {
+                self.identify_reply = Some(IdResponse::new(self.id));
+            }
         }
+    }
 
-        unimplemented!()
+    /// Handle a `Ready`-class handshake frame, gating whether this
+    /// device may currently act as the active listener / send data.
+    fn ready(&mut self, ty: MessageType) {
+        match ty {
+            MessageType::ReadyForCommand => self.data_ready = false,
+            MessageType::Unlisten => self.listener_active = false,
+            MessageType::SendDataReady => self.data_ready = true,
+            _ => {}
+        }
     }
 
-    fn out_signals(&self) -> BusSignalsOut
+    /// Pull the next `Message` of an in-progress `Command::Id` reply, if
+    /// any.
+    fn out_signals(&mut self) -> (BusSignalsOut, Option<Message>)
     {
-        BusSignalsOut {
-            // hold daisy_out low when unconfigured
-            daisy_out: self.configured,
+        let reply = self.identify_reply.as_mut().and_then(Iterator::next);
+        if reply.is_none() {
+            self.identify_reply = None;
+        }
+
+        (
+            BusSignalsOut {
+                // hold daisy_out low when unconfigured
+                daisy_out: self.configured,
+            },
+            reply,
+        )
+    }
+}
+
+impl AddressableDevice for BusDevice {
+    fn len(&self) -> usize {
+        match &self.backing {
+            Backing::Ram(bytes) => bytes.len(),
+            Backing::Rom(bytes) => bytes.len(),
+            Backing::Mailbox(_) => 1,
+        }
+    }
+
+    fn read(&self, addr: u16) -> Option<u8> {
+        match &self.backing {
+            Backing::Ram(bytes) => bytes.get(addr as usize).copied(),
+            Backing::Rom(bytes) => bytes.get(addr as usize).copied(),
+            Backing::Mailbox(value) => if addr == 0 { Some(*value) } else { None },
         }
     }
-}
\ No newline at end of file
+
+    fn write(&mut self, addr: u16, value: u8) -> bool {
+        match &mut self.backing {
+            Backing::Ram(bytes) => match bytes.get_mut(addr as usize) {
+                Some(b) => {
+                    *b = value;
+                    true
+                }
+                None => false,
+            },
+            Backing::Rom(_) => false,
+            Backing::Mailbox(slot) => {
+                if addr == 0 {
+                    *slot = value;
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_unconfigured_device_ignores_command_without_daisy_in() {
+    let mut dev = BusDevice::default();
+    dev.command(Command::Config, 3, BusSignalsIn { daisy_in: false });
+    assert!(!dev.configured);
+    assert_eq!(dev.address, None);
+}
+
+#[test]
+fn test_command_config_latches_address_and_asserts_daisy_out() {
+    let mut dev = BusDevice::default();
+    dev.command(Command::Config, 3, BusSignalsIn { daisy_in: true });
+    assert!(dev.configured);
+    assert_eq!(dev.address, Some(3));
+    assert!(dev.out_signals().0.daisy_out);
+}
+
+#[test]
+fn test_command_id_emits_five_nibble_reply() {
+    let mut dev = BusDevice { id: 0x9_0_0_0_1, ..BusDevice::default() };
+    dev.command(Command::Id, 0, BusSignalsIn { daisy_in: true });
+
+    let mut nibbles = Vec::new();
+    while let (_, Some(reply)) = dev.out_signals() {
+        nibbles.push(reply.data() & 0xf);
+    }
+
+    assert_eq!(nibbles, vec![9, 0, 0, 0, 1]);
+}
+
+#[test]
+fn test_ready_handshake_gates_listener_and_data_ready() {
+    let mut dev = BusDevice { listener_active: true, ..BusDevice::default() };
+
+    dev.ready(MessageType::Unlisten);
+    assert!(!dev.listener_active);
+
+    dev.ready(MessageType::SendDataReady);
+    assert!(dev.data_ready);
+
+    dev.ready(MessageType::ReadyForCommand);
+    assert!(!dev.data_ready);
+}
+
+#[test]
+fn test_ram_id_decodes_to_writable_backing_of_the_right_size() {
+    // nibble 0 (size) 0xe -> 14 - 14 = 0, 1KB; nibble 2 (device type) 0 -> RAM
+    let mut dev = BusDevice::new(0xe_0_0_0_0, None);
+    assert_eq!(dev.len(), 1024);
+    assert!(dev.write(0, 0x42));
+    assert_eq!(dev.read(0), Some(0x42));
+    assert_eq!(dev.read(1024), None);
+    assert!(!dev.write(1024, 0));
+}
+
+#[test]
+fn test_rom_id_decodes_to_read_only_backing() {
+    static ROM: [u8; 4] = [1, 2, 3, 4];
+    // nibble 2 (device type) 1 -> ROM
+    let mut dev = BusDevice::new(0xe_0_1_0_0, Some(&ROM));
+    assert_eq!(dev.len(), 4);
+    assert_eq!(dev.read(2), Some(3));
+    assert!(!dev.write(0, 0xff));
+}
+
+#[test]
+fn test_mmio_mailbox_id_decodes_to_single_byte_backing() {
+    // nibble 2 (device type) 0xf -> memory-mapped-io, nibble 3 (subtype) 0 -> HP-IL mailbox
+    let mut dev = BusDevice::new(0x0_0_f_0_0, None);
+    assert_eq!(dev.len(), 1);
+    assert!(dev.write(0, 7));
+    assert_eq!(dev.read(0), Some(7));
+    assert_eq!(dev.read(1), None);
+}
+
+#[test]
+fn test_ram_id_round_trips_through_identify_reply() {
+    // `BusDevice::new` backs a device off the same `id` nibbles as
+    // `Command::Id` reports over the wire: its reply must name the same
+    // size, since both are decoded from `id` (see `Backing::from_id` and
+    // `IdResponse`).
+    let mut dev = BusDevice::new(0xe_0_0_0_0, None);
+    assert_eq!(dev.len(), 1024);
+
+    dev.command(Command::Id, 0, BusSignalsIn { daisy_in: true });
+    let mut nibbles = Vec::new();
+    while let (_, Some(reply)) = dev.out_signals() {
+        nibbles.push((reply.data() & 0xf) as u32);
+    }
+
+    // nibble 0 (size) and nibble 2 (device type), per `IdResponse`'s
+    // most-significant-first emission order
+    assert_eq!(nibbles[0], 0xe);
+    assert_eq!(nibbles[2], 0);
+    assert_eq!(Backing::size_bytes(14, nibbles[0]), dev.len());
+}
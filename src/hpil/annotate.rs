@@ -0,0 +1,184 @@
+//! Stacked annotation layer on top of `PollPhy`, following the layered
+//! protocol-decoder model used by logic-analyzer frontends: a low "bits"
+//! layer records what was literally on the wire (the sync bit, the
+//! control field, the data byte), while a high "message" layer records
+//! what it means (a named `MessageType`, or an unclassified
+//! addressing/other frame). Both are tagged with the sample range they
+//! span so a GUI can render HP-IL traffic at multiple zoom levels from
+//! one decode pass.
+
+use super::{MessageClass, MessageType, PhySample, PollPhy};
+
+/// An event spanning a range of samples, tagged with what it represents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Annotation<K> {
+    /// first sample (inclusive) this annotation spans
+    pub start_sample: u64,
+    /// last sample (exclusive) this annotation spans
+    pub end_sample: u64,
+    pub kind: K,
+}
+
+/// Low-level "what was on the wire" annotation: one per resolved bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitKind {
+    /// the leading sync bit
+    Sync(bool),
+    /// one of the two remaining control bits
+    Control(bool),
+    /// one of the 8 data bits
+    Data(bool),
+}
+
+impl BitKind {
+    fn from_bit_offs(bit_offs: u8, value: bool) -> Self {
+        match bit_offs {
+            0 => BitKind::Sync(value),
+            1 | 2 => BitKind::Control(value),
+            _ => BitKind::Data(value),
+        }
+    }
+}
+
+/// High-level "what it means" annotation: one per finalized message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    /// one of the fixed-encoding message types `MessageType` recognizes
+    Type(MessageType),
+    /// an unrecognized `Command`-class frame, presumed to be a
+    /// device-addressing command (`BusDevice`'s `Command::Config`/`Id`)
+    Addressing(MessageClass, u8),
+    /// any other unrecognized frame
+    Other(MessageClass, u8),
+}
+
+/// One push's worth of annotations: at most one bit resolves and at most
+/// one message finalizes per sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AnnotationEvent {
+    pub bit: Option<Annotation<BitKind>>,
+    pub message: Option<Annotation<MessageKind>>,
+}
+
+/// Wraps a `PollPhy`, turning its decoded bits and messages into
+/// sample-range-tagged `Annotation`s.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Annotator {
+    phy: PollPhy,
+    // the in-progress frame's leading sync bit's `BitEvent::start_sample`,
+    // if its sync bit has resolved
+    sync_start: Option<u64>,
+}
+
+impl Annotator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed one polled line-level sample through the phy, emitting a bit
+    /// annotation whenever a bit resolves and a message annotation
+    /// whenever those bits complete a `Message`.
+    pub fn push(&mut self, sample: PhySample) -> AnnotationEvent {
+        let (message, _errors, bit_event) = self.phy.push_traced(sample);
+
+        let bit = bit_event.map(|e| {
+            if e.bit_offs == 0 {
+                self.sync_start = Some(e.start_sample);
+            }
+            Annotation {
+                start_sample: e.start_sample,
+                end_sample: e.end_sample,
+                kind: BitKind::from_bit_offs(e.bit_offs, e.value),
+            }
+        });
+
+        let message = message.map(|m| {
+            let kind = match MessageType::from_message(m) {
+                Some(t) => MessageKind::Type(t),
+                None if m.class() == MessageClass::Command => {
+                    MessageKind::Addressing(m.class(), m.data())
+                }
+                None => MessageKind::Other(m.class(), m.data()),
+            };
+            let start_sample = self.sync_start.unwrap_or_else(|| self.phy.sample_count());
+            self.sync_start = None;
+            Annotation {
+                start_sample,
+                end_sample: self.phy.sample_count(),
+                kind,
+            }
+        });
+
+        AnnotationEvent { bit, message }
+    }
+
+    /// Pull the next `PhySample` of an in-progress retransmission, if
+    /// any; see `PollPhy::out_signals`.
+    pub fn out_signals(&mut self) -> Option<PhySample> {
+        self.phy.out_signals()
+    }
+}
+
+#[test]
+fn test_annotator_emits_type_annotation_for_known_message() {
+    use super::{Message, PhyBitEncoder, DEFAULT_SAMPLES_PER_SYMBOL};
+
+    let message = Message::new(0b100_10010000);
+    let mut annotator = Annotator::new();
+
+    let mut last_message_annotation = None;
+    for s in PhyBitEncoder::new(message, DEFAULT_SAMPLES_PER_SYMBOL) {
+        let event = annotator.push(s);
+        if let Some(a) = event.message {
+            last_message_annotation = Some(a);
+        }
+    }
+
+    let annotation = last_message_annotation.expect("message should have resolved");
+    assert_eq!(annotation.kind, MessageKind::Type(MessageType::ReadyForCommand));
+    assert_eq!(annotation.start_sample, 0);
+}
+
+#[test]
+fn test_annotator_emits_bit_annotations_for_each_resolved_bit() {
+    use super::{Message, PhyBitEncoder, DEFAULT_SAMPLES_PER_SYMBOL};
+
+    let message = Message::new(0b100_10010000);
+    let mut annotator = Annotator::new();
+
+    let mut bit_annotations = Vec::new();
+    for s in PhyBitEncoder::new(message, DEFAULT_SAMPLES_PER_SYMBOL) {
+        if let Some(a) = annotator.push(s).bit {
+            bit_annotations.push(a);
+        }
+    }
+
+    // sync bit + 10 remaining bits
+    assert_eq!(bit_annotations.len(), 11);
+    assert_eq!(bit_annotations[0].kind, BitKind::Sync(true));
+}
+
+#[test]
+fn test_annotator_message_start_sample_survives_an_idle_gap_before_the_frame() {
+    // idle line samples ahead of the frame must not drift the message
+    // annotation's start_sample earlier than its own sync bit.
+    use super::{Message, PhyBitEncoder, DEFAULT_SAMPLES_PER_SYMBOL};
+
+    let message = Message::new(0b100_10010000);
+    let mut annotator = Annotator::new();
+
+    let gap = 1;
+    for _ in 0..gap {
+        annotator.push(PhySample::Zero);
+    }
+
+    let mut last_message_annotation = None;
+    for s in PhyBitEncoder::new(message, DEFAULT_SAMPLES_PER_SYMBOL) {
+        if let Some(a) = annotator.push(s).message {
+            last_message_annotation = Some(a);
+        }
+    }
+
+    let annotation = last_message_annotation.expect("message should have resolved");
+    assert_eq!(annotation.start_sample, gap);
+}
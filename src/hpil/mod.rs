@@ -0,0 +1,854 @@
+pub mod annotate;
+pub mod frontend;
+pub mod trace;
+
+/// `PollPhy` manages decoding of sampled signals into `Message`s
+/// 
+/// It presumes we're polling the fabric approximately every 0.5 microseconds
+/// (iow: twice the expected rate), but allows polling at exactly 1 microsecond,
+/// or faster. It only requires that no edges are missed. Sampling faster will
+/// require more internal storage.
+/// 
+/// It operates on a simplified 3 state input rather than analog voltages.
+/// 
+/// Electrical signals of HP-IL use 3-states: positive, zero, and negative. These
+/// differentials are the measurement of voltage between the 2 HP-IL conductors.
+/// 
+/// -1.5V, 0V, and +1.5V are the levels.
+/// 1 microsecond is used as the pulse width.
+/// 
+/// See `PhyBitDecoder` for more details.
+#[derive(Debug,Clone,PartialEq,Eq,Default)]
+pub struct PollPhy {
+    bit_decode: PhyBitDecoder,
+
+    // next bit to be filled in
+    message_bit_offs: u8,
+    // accumulated message bits
+    // note: a 0 sync bit leaves this at 0; `message_bit_offs > 0` is what
+    // marks a recieved sync bit
+    message_bits: u16,
+
+    // when we identify the message_bits as having a _prefix_ that indicates the
+    // need to be retransmitted, we should begin retransmission.
+    // HP-IL spec refers to this as "echo" vs "hold".
+    retransmit: Option<PhyBitEncoder>,
+
+    // samples pushed since the last bit boundary. Used to keep
+    // `decode_bit`'s sliding window from reaching back across a bit it
+    // already resolved.
+    samples_since_bit: u8,
+
+    // framing errors noticed while resolving the in-progress message,
+    // reset once it (or the next one) completes
+    frame_errors: FrameErrors,
+}
+
+/// Framing problems noticed while resolving a message's bits.
+///
+/// Modeled on the error flags RFID/ISO14443 sniffing tools attach to a
+/// captured frame, so a `hpil::trace::Sniffer` (or any other consumer)
+/// can tell a clean decode from one that only squeaked by on tolerance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FrameErrors {
+    /// the leading sync bit decoded, but not as a clean (perfect-score)
+    /// match
+    pub bad_sync: bool,
+    /// a control/data bit decoded, but not as a clean match, consistent
+    /// with a missed edge or a symbol shorter/longer than expected
+    pub truncated_symbol: bool,
+    /// a sampled line level didn't correspond to any of the 3 known
+    /// states. Never set by this software-only phy; reserved for a
+    /// hardware sampling frontend that can observe invalid levels.
+    pub unexpected_level: bool,
+}
+
+impl FrameErrors {
+    /// whether any error flag is set
+    pub fn any(&self) -> bool {
+        self.bad_sync || self.truncated_symbol || self.unexpected_level
+    }
+}
+
+/// One resolved bit, tagged with the sample range it spanned.
+///
+/// Emitted by `PollPhy::push_traced` as a message's bits are built up;
+/// consumed by `hpil::annotate` to produce sample-range-tagged
+/// annotations without duplicating the decode loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitEvent {
+    /// position within the message: `0` is the leading sync bit, `1..=2`
+    /// are the remaining control bits, `3..=10` are the data bits
+    pub bit_offs: u8,
+    pub value: bool,
+    /// first sample (inclusive) this bit's symbol started at
+    pub start_sample: u64,
+    /// last sample (exclusive) this bit's symbol ended at
+    pub end_sample: u64,
+}
+
+/// These 3 states, in combination with timing, are used to encode bits. The
+/// first bit always has a special "sync" format.
+/// 
+/// The following is the bit decoding. `N` is a negative level, `P` is a positive
+/// level, and `Z` is the zero level.
+/// 
+/// - 1: `PNZZ`
+/// - 0: `NPZZ`
+/// - 1 sync: `PNPNZZ`
+/// - 0 sync: `NPNPZZ`
+/// 
+#[derive(Debug,Clone,PartialEq,Eq,Default)]
+pub struct PhyBitDecoder {
+    // packed into 2 bit representations,
+    // `32 / 2 = 16` samples possible
+    //
+    // theoretically allows sampling at `(16/6) = 2 2/3` times the actual edge
+    // rate.
+    //
+    // lower bits are older, higher bits are newer
+    // filled in high bits first:
+    //      |xxxxxxxxxxxxxxxx|
+    //      |Axxxxxxxxxxxxxxx| (pushed sample A)
+    //      |BAxxxxxxxxxxxxxx| (pushed sample B)
+    //      ...
+    //      |PONMLKJIHGFEDCBA|
+    //      |QPONMLKJIHGFEDCB| (pushed sample Q, dropped A)
+    packed_samples: u32,
+
+    // bit (sample / 2) offset to be filled in next. all samples before this
+    // are valid and can be examined.
+    packed_sample_offs: u8,
+
+    // total number of samples ever pushed, regardless of how many are
+    // still buffered. Used by trace/sniffer consumers to tag decoded
+    // frames with the sample position they started at.
+    sample_count: u64,
+}
+
+impl PhyBitDecoder {
+    pub fn samples(&self) -> PhySampleIter<'_> {
+        PhySampleIter {
+            p: self,
+            sample_offs: 0,
+        }
+    }
+
+    /// total number of samples ever pushed into this decoder
+    pub fn sample_count(&self) -> u64 {
+        self.sample_count
+    }
+
+    /// push a new sample into the Phy
+    pub fn push(&mut self, sample: PhySample) {
+        self.sample_count += 1;
+
+        assert!((self.packed_sample_offs & 1) == 0);
+
+        if self.packed_sample_offs == 32 {
+            // we essentially cap at 32 bits. old data gets shifted off below
+            self.packed_sample_offs -= 2;
+        }
+
+        assert!(self.packed_sample_offs <= 30);
+
+        // XXX: consider if avoiding a constant rotation might make sense
+        self.packed_samples = self.packed_samples.wrapping_shr(2);
+
+        // NOTE: 32 here is the number of bits in `packed_samples`, and `2` is the bits-per-sample
+        self.packed_samples |= (sample.as_bits() as u32) << (32 - 2);
+        self.packed_sample_offs += 2;
+    }
+}
+
+/// Iterate over samples recieved from newest to oldest
+pub struct PhySampleIter<'a> {
+    p: &'a PhyBitDecoder,
+    sample_offs: u8,
+}
+
+impl<'a> Iterator for PhySampleIter<'a> {
+    type Item = PhySample;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.sample_offs == self.p.packed_sample_offs {
+            None
+        } else {
+            let shift = 32 - self.sample_offs - 2;
+            let mask = 0b11 << shift;
+            self.sample_offs += 2;
+            Some(PhySample::from_bits(((self.p.packed_samples & mask) >> shift) as u8).unwrap())
+        }
+    }
+}
+
+#[test]
+fn test_sample_iter() {
+    let mut phy = PhyBitDecoder::default();
+
+    let samples = [
+        PhySample::Neg,
+        PhySample::Pos,
+        PhySample::Zero,
+    ];
+
+    for &s in samples.iter().rev() {
+        phy.push(s);
+    }
+
+    let rs: Vec<PhySample> = phy.samples().collect();
+
+    assert_eq!(&samples[..], &rs[..]);
+}
+
+impl PollPhy {
+    /// Minimum fraction of sampled positions that must agree with the
+    /// expected template for a sync/bit decode to be accepted.
+    ///
+    /// `3/4` tolerates a missed edge or an extra/short sample from
+    /// oversampling without accepting noise as a valid frame.
+    const MATCH_THRESHOLD: (u32, u32) = (3, 4);
+
+    /// Extra samples beyond the `DEFAULT_SAMPLES_PER_SYMBOL` boundary
+    /// `at_symbol_boundary` still waits for before giving up on it,
+    /// absorbing the one extra sample a missed edge produces at the
+    /// oversampling rate `PollPhy` actually decodes at.
+    ///
+    /// Only applied at that one (highest, and in practice only used)
+    /// rate: widening every `1..DEFAULT_SAMPLES_PER_SYMBOL` boundary the
+    /// same way would let a lower-rate hypothesis's sliding window drift
+    /// onto an interior sample of the real, still-arriving symbol and
+    /// falsely pass `MATCH_THRESHOLD` against it.
+    const EDGE_SLOP: u8 = 1;
+
+    /// Look for the leading sync bit in the buffered samples and, if one
+    /// is found, record it as the first message bit.
+    ///
+    /// Returns `true` once a sync bit has been recognized (including on
+    /// repeated calls after it already has been).
+    pub fn check_seq(&mut self) -> bool {
+        if self.message_bit_offs > 0 {
+            return true;
+        }
+
+        self.try_resolve_sync().is_some()
+    }
+
+    /// Shared by `check_seq` and `push_traced`: if at a symbol boundary,
+    /// attempt to resolve the leading sync bit from the buffered samples
+    /// and, on success, record it as the first message bit and return
+    /// its `(bit, samples consumed)`.
+    fn try_resolve_sync(&mut self) -> Option<(bool, u8)> {
+        // idle-line samples ahead of a sync bit belong to no symbol: hold
+        // the count at the widest sync boundary so the search keeps
+        // sliding over the newest samples instead of passing every
+        // boundary for good.
+        let widest = TEMPLATE_SYNC_1.len() as u8 * DEFAULT_SAMPLES_PER_SYMBOL + Self::EDGE_SLOP;
+        self.samples_since_bit = self.samples_since_bit.min(widest);
+        if !self.at_symbol_boundary(TEMPLATE_SYNC_1.len() as u8) {
+            return None;
+        }
+
+        match self.bit_decode.decode_bit(true, DEFAULT_SAMPLES_PER_SYMBOL, Self::MATCH_THRESHOLD) {
+            Some(m) if m.samples <= self.samples_since_bit => {
+                self.message_bits = (m.bit as u16) << 10;
+                self.message_bit_offs = 1;
+                // Unlike the per-bit case, any slack between `m.samples`
+                // and `samples_since_bit` here is idle-line samples from
+                // before the sync template started matching, not an
+                // early sample of the next symbol — carrying it forward
+                // would misalign every subsequent bit boundary in the
+                // frame. The leading sync always starts the bit clock
+                // fresh.
+                self.samples_since_bit = 0;
+                self.frame_errors.bad_sync = m.score.0 != m.score.1;
+                Some((m.bit, m.samples))
+            }
+            _ => None,
+        }
+    }
+
+    /// Number of bits of the in-progress message resolved so far (`0`
+    /// means no sync bit has been recognized yet).
+    pub fn bit_offs(&self) -> u8 {
+        self.message_bit_offs
+    }
+
+    /// total number of samples ever pushed into the underlying phy; see
+    /// `PhyBitDecoder::sample_count`.
+    pub fn sample_count(&self) -> u64 {
+        self.bit_decode.sample_count()
+    }
+
+    /// Whether a `template_len`-symbol template could have just finished
+    /// arriving, for some nominal `1..=DEFAULT_SAMPLES_PER_SYMBOL` polling
+    /// rate, within `EDGE_SLOP` samples.
+    ///
+    /// Gates decode attempts to these boundaries so a correlation against
+    /// an interior window of a wider (oversampled) symbol can't alias as
+    /// a complete narrower one, while still allowing for the one
+    /// extra/missing sample a missed edge produces (`decode_bit`'s own
+    /// correlation, via the ring buffer it pulls from, tolerates the
+    /// resulting misalignment — see `MATCH_THRESHOLD`).
+    fn at_symbol_boundary(&self, template_len: u8) -> bool {
+        let highest = template_len * DEFAULT_SAMPLES_PER_SYMBOL;
+        (1..DEFAULT_SAMPLES_PER_SYMBOL).any(|repeat| self.samples_since_bit == template_len * repeat)
+            || (highest..=highest + Self::EDGE_SLOP).contains(&self.samples_since_bit)
+    }
+
+    /// Feed one freshly polled line-level sample into the phy.
+    ///
+    /// Advances sync/bit decode and, once a full 11-bit message has been
+    /// recognized, returns it. If the message's class indicates it needs
+    /// to be relayed around the loop (see `requires_echo`), retransmission
+    /// of it is armed so that `out_signals` starts emitting it on
+    /// subsequent calls.
+    pub fn push(&mut self, sample: PhySample) -> Option<Message> {
+        self.push_traced(sample).0
+    }
+
+    /// Same as `push`, but also reports the framing errors noticed while
+    /// resolving the message that just completed and, whenever a bit (or
+    /// the sync bit) resolved, a `BitEvent` describing it. Intended for
+    /// sniffer/trace/annotation consumers that need finer-grained
+    /// insight than the completed `Message` payload alone.
+    pub fn push_traced(&mut self, sample: PhySample) -> (Option<Message>, FrameErrors, Option<BitEvent>) {
+        self.bit_decode.push(sample);
+        self.samples_since_bit = self.samples_since_bit.saturating_add(1);
+
+        // (bit_offs, value, samples consumed) of whichever bit just
+        // resolved this push, if any
+        let mut resolved: Option<(u8, bool, u8)> = None;
+
+        if self.message_bit_offs == 0 {
+            match self.try_resolve_sync() {
+                Some((bit, samples)) => resolved = Some((0, bit, samples)),
+                None => return (None, FrameErrors::default(), None),
+            }
+        } else if self.message_bit_offs < 11 && self.at_symbol_boundary(TEMPLATE_BIT_1.len() as u8) {
+            if let Some(m) = self.bit_decode.decode_bit(false, DEFAULT_SAMPLES_PER_SYMBOL, Self::MATCH_THRESHOLD) {
+                if m.samples <= self.samples_since_bit {
+                    let bit_offs = self.message_bit_offs;
+                    self.message_bits |= (m.bit as u16) << (10 - bit_offs);
+                    self.message_bit_offs += 1;
+                    self.samples_since_bit -= m.samples;
+                    if m.score.0 != m.score.1 {
+                        self.frame_errors.truncated_symbol = true;
+                    }
+                    resolved = Some((bit_offs, m.bit, m.samples));
+                }
+            }
+        }
+
+        let bit_event = resolved.map(|(bit_offs, value, samples)| {
+            let end_sample = self.bit_decode.sample_count();
+            BitEvent {
+                bit_offs,
+                value,
+                start_sample: end_sample - samples as u64,
+                end_sample,
+            }
+        });
+
+        if self.message_bit_offs < 11 {
+            return (None, FrameErrors::default(), bit_event);
+        }
+
+        let message = Message::new(self.message_bits);
+        let errors = self.frame_errors;
+        self.message_bit_offs = 0;
+        self.message_bits = 0;
+        self.frame_errors = FrameErrors::default();
+
+        if Self::requires_echo(message) {
+            self.retransmit = Some(PhyBitEncoder::new(message, DEFAULT_SAMPLES_PER_SYMBOL));
+        }
+
+        (Some(message), errors, bit_event)
+    }
+
+    /// Whether a received frame needs to be retransmitted ("echo") rather
+    /// than only consumed locally ("hold").
+    ///
+    /// HP-IL is wired as a loop, so bus-management frames (`Command`,
+    /// `Ready`, `Identify`) must reach every downstream device and are
+    /// always echoed. `DataOrEnd` frames are addressed point-to-point
+    /// between the controller and the active listener/talker and are
+    /// held instead.
+    fn requires_echo(message: Message) -> bool {
+        !matches!(message.class(), MessageClass::DataOrEnd)
+    }
+
+    /// Pull the next `PhySample` of an in-progress retransmission, if
+    /// any, to drive onto the bus this tick.
+    ///
+    /// Expected to be called once per poll tick, in step with `push`, by
+    /// the device bridging `daisy_in`/`daisy_out`.
+    pub fn out_signals(&mut self) -> Option<PhySample> {
+        let sample = self.retransmit.as_mut()?.next();
+        if sample.is_none() {
+            self.retransmit = None;
+        }
+        sample
+    }
+}
+
+/// Number of samples expected per nominal symbol period when polling at
+/// the rate `PollPhy`'s docs recommend (twice the edge rate).
+pub const DEFAULT_SAMPLES_PER_SYMBOL: u8 = 2;
+
+/// One tri-state line-level template, written oldest-sample-first, for a
+/// data bit or the leading sync bit. See `PhyBitDecoder`'s docs for the
+/// nominal (non-oversampled, non-jittered) waveform each one models.
+type Template = &'static [PhySample];
+
+const TEMPLATE_BIT_1: Template = &[PhySample::Pos, PhySample::Neg, PhySample::Zero, PhySample::Zero];
+const TEMPLATE_BIT_0: Template = &[PhySample::Neg, PhySample::Pos, PhySample::Zero, PhySample::Zero];
+const TEMPLATE_SYNC_1: Template = &[PhySample::Pos, PhySample::Neg, PhySample::Pos, PhySample::Neg, PhySample::Zero, PhySample::Zero];
+const TEMPLATE_SYNC_0: Template = &[PhySample::Neg, PhySample::Pos, PhySample::Neg, PhySample::Pos, PhySample::Zero, PhySample::Zero];
+
+/// Outcome of correlating buffered samples against a bit/sync template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitMatch {
+    /// the decoded bit value
+    pub bit: bool,
+    /// number of buffered samples the match consumed
+    pub samples: u8,
+    /// agreeing/total sampled positions for the winning hypothesis. A
+    /// match below `(1, 1)` is still accepted as long as it clears
+    /// `threshold`, but it's a sign of a missed edge or other jitter.
+    pub score: (u32, u32),
+}
+
+impl PhyBitDecoder {
+    /// Try to decode the next bit (or, when `sync` is set, the special
+    /// leading sync bit) from the buffered samples by template
+    /// correlation rather than exact equality.
+    ///
+    /// Each of `1..=samples_per_symbol` samples-per-symbol hypotheses is
+    /// tried by expanding the expected tri-state template to that width
+    /// and sliding it against the most recently buffered samples; the
+    /// hypothesis (and bit value) with the highest fraction of agreeing
+    /// samples wins, provided it clears `threshold` (given as a
+    /// `(numerator, denominator)` fraction, e.g. `(3, 4)`). This is what
+    /// tolerates oversampling jitter and the occasional missed-edge
+    /// sample that exact matching can't.
+    ///
+    /// Hypotheses are tried highest-repeat-first so that, on a tie, the
+    /// one explaining the most buffered samples wins: a full-width
+    /// correlation that still clears `threshold` is more trustworthy
+    /// than a same-scoring partial one that a missed edge could make
+    /// coincidentally agree with a narrower template.
+    pub fn decode_bit(&self, sync: bool, samples_per_symbol: u8, threshold: (u32, u32)) -> Option<BitMatch> {
+        let (one, zero) = if sync {
+            (TEMPLATE_SYNC_1, TEMPLATE_SYNC_0)
+        } else {
+            (TEMPLATE_BIT_1, TEMPLATE_BIT_0)
+        };
+
+        let available = self.packed_sample_offs as u32 / 2;
+
+        let mut best: Option<BitMatch> = None;
+        let mut best_score = (0u32, 1u32);
+
+        for repeat in (1..=samples_per_symbol.max(1)).rev() {
+            for &(bit, template) in &[(true, one), (false, zero)] {
+                let total = template.len() as u32 * repeat as u32;
+                if total > available {
+                    // not enough buffered samples for this hypothesis
+                    continue;
+                }
+
+                let mut agree = 0u32;
+                let mut seen = 0u32;
+                // `samples()` yields newest-first; templates are written
+                // oldest-first, so walk the template in reverse to line
+                // the two up.
+                let mut it = self.samples();
+                'template: for &sym in template.iter().rev() {
+                    for _ in 0..repeat {
+                        let s = match it.next() {
+                            Some(s) => s,
+                            None => break 'template,
+                        };
+                        seen += 1;
+                        if s == sym {
+                            agree += 1;
+                        }
+
+                        // bail out once this hypothesis can no longer beat
+                        // the best one found so far, even with every
+                        // remaining sample agreeing.
+                        let max_possible = agree + (total - seen);
+                        if max_possible * best_score.1 <= best_score.0 * total {
+                            break 'template;
+                        }
+                    }
+                }
+
+                if seen == total && agree * best_score.1 > best_score.0 * total {
+                    best_score = (agree, total);
+                    best = Some(BitMatch { bit, samples: total as u8, score: (agree, total) });
+                }
+            }
+        }
+
+        match best {
+            Some(m) if best_score.0 * threshold.1 >= threshold.0 * best_score.1 => Some(m),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_decode_bit_exact() {
+    let mut phy = PhyBitDecoder::default();
+    for &s in TEMPLATE_BIT_1.iter() {
+        // push each symbol `DEFAULT_SAMPLES_PER_SYMBOL` times, oldest first
+        for _ in 0..DEFAULT_SAMPLES_PER_SYMBOL {
+            phy.push(s);
+        }
+    }
+
+    let m = phy.decode_bit(false, DEFAULT_SAMPLES_PER_SYMBOL, PollPhy::MATCH_THRESHOLD).unwrap();
+    assert!(m.bit);
+    assert_eq!(m.samples as u32, TEMPLATE_BIT_1.len() as u32 * DEFAULT_SAMPLES_PER_SYMBOL as u32);
+}
+
+#[test]
+fn test_decode_bit_tolerates_jitter() {
+    let mut phy = PhyBitDecoder::default();
+    // one symbol sampled a single extra time, as if polling slightly
+    // faster than the nominal rate would produce
+    let extra = [
+        (PhySample::Neg, 3),
+        (PhySample::Pos, 2),
+        (PhySample::Zero, 2),
+        (PhySample::Zero, 2),
+    ];
+    for &(s, n) in extra.iter() {
+        for _ in 0..n {
+            phy.push(s);
+        }
+    }
+
+    let m = phy.decode_bit(false, 3, PollPhy::MATCH_THRESHOLD).unwrap();
+    assert!(!m.bit);
+}
+
+#[test]
+fn test_check_seq() {
+    let mut poll = PollPhy::default();
+    for &s in TEMPLATE_SYNC_1.iter() {
+        for _ in 0..DEFAULT_SAMPLES_PER_SYMBOL {
+            poll.bit_decode.push(s);
+            poll.samples_since_bit += 1;
+        }
+    }
+
+    assert!(poll.check_seq());
+    assert_eq!(poll.message_bit_offs, 1);
+    assert_eq!(poll.message_bits, 1 << 10);
+    // already recognized; repeated calls stay true without re-scanning
+    assert!(poll.check_seq());
+}
+
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum PhySample {
+    Zero,
+    Pos,
+    Neg,
+}
+
+impl PhySample {
+    // Note: 0 is avoided so it can be used in packed samples to represent the lack of a sample
+    fn as_bits(self) -> u8 {
+        match self {
+            Self::Zero => 0b11,
+            Self::Pos => 0b01,
+            Self::Neg => 0b10,
+        }
+    }
+
+    fn from_bits(b: u8) -> Option<Self> {
+        match b {
+            0b11 => Some(Self::Zero),
+            0b01 => Some(Self::Pos),
+            0b10 => Some(Self::Neg),
+            _ => None,
+        }
+    }
+}
+
+/// Encodes an 11-bit `Message` back into the tri-state `PhySample`
+/// sequence `PhyBitDecoder` expects, for retransmission or for driving a
+/// bus from software. Mirrors `PhyBitDecoder::decode_bit`'s templates, so
+/// at `samples_per_symbol` of `1..=DEFAULT_SAMPLES_PER_SYMBOL` what this
+/// emits decodes back through `PollPhy` to the same `Message`; higher
+/// rates are not decoded by `PollPhy`.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct PhyBitEncoder {
+    message: Message,
+    samples_per_symbol: u8,
+
+    // next message bit to emit: 0 is the leading sync bit, 10 is the last
+    // data bit. 11 means the message is fully emitted.
+    bit_offs: u8,
+    // position within the current bit's (possibly oversampled) template
+    template_offs: u8,
+}
+
+impl PhyBitEncoder {
+    pub fn new(message: Message, samples_per_symbol: u8) -> Self {
+        PhyBitEncoder {
+            message,
+            samples_per_symbol: samples_per_symbol.max(1),
+            bit_offs: 0,
+            template_offs: 0,
+        }
+    }
+
+    fn current_bit(&self) -> bool {
+        (self.message.raw >> (10 - self.bit_offs)) & 1 != 0
+    }
+
+    fn current_template(&self) -> Template {
+        match (self.bit_offs, self.current_bit()) {
+            (0, true) => TEMPLATE_SYNC_1,
+            (0, false) => TEMPLATE_SYNC_0,
+            (_, true) => TEMPLATE_BIT_1,
+            (_, false) => TEMPLATE_BIT_0,
+        }
+    }
+}
+
+impl Iterator for PhyBitEncoder {
+    type Item = PhySample;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bit_offs > 10 {
+            return None;
+        }
+
+        let template = self.current_template();
+        let symbol = template[(self.template_offs / self.samples_per_symbol) as usize];
+
+        self.template_offs += 1;
+        if self.template_offs == template.len() as u8 * self.samples_per_symbol {
+            self.template_offs = 0;
+            self.bit_offs += 1;
+        }
+
+        Some(symbol)
+    }
+}
+
+#[test]
+fn test_encoder_sync_bit_round_trips_through_decoder() {
+    let message = Message::new(0b100_10010000);
+    let mut phy = PhyBitDecoder::default();
+
+    // the leading sync symbol is the only thing that fits in the
+    // decoder's 16-sample window on its own
+    let sync_samples = TEMPLATE_SYNC_1.len() * DEFAULT_SAMPLES_PER_SYMBOL as usize;
+    for s in PhyBitEncoder::new(message, DEFAULT_SAMPLES_PER_SYMBOL).take(sync_samples) {
+        phy.push(s);
+    }
+
+    let sync = phy.decode_bit(true, DEFAULT_SAMPLES_PER_SYMBOL, PollPhy::MATCH_THRESHOLD).unwrap();
+    assert!(sync.bit);
+}
+
+#[test]
+fn test_push_reconstructs_sent_message() {
+    let message = Message::new(0b100_10010000);
+    let mut poll = PollPhy::default();
+
+    let mut decoded = None;
+    for s in PhyBitEncoder::new(message, DEFAULT_SAMPLES_PER_SYMBOL) {
+        if let Some(m) = poll.push(s) {
+            decoded = Some(m);
+        }
+    }
+
+    assert_eq!(decoded, Some(message));
+}
+
+#[test]
+fn test_push_tolerates_missed_edge_via_push() {
+    // simulate a missed edge: the last bit's first level change arrives
+    // one poll tick late, so `push` samples one extra copy of its first
+    // level before the symbol continues at the nominal rate. Unlike
+    // `test_decode_bit_tolerates_jitter`, this drives the jitter through
+    // `PollPhy::push` itself, so it also exercises `at_symbol_boundary`'s
+    // gating, not just `decode_bit`.
+    let message = Message::new(0b100_10010000);
+    let samples: Vec<PhySample> =
+        PhyBitEncoder::new(message, DEFAULT_SAMPLES_PER_SYMBOL).collect();
+    let last_bit_width = TEMPLATE_BIT_1.len() * DEFAULT_SAMPLES_PER_SYMBOL as usize;
+    let split = samples.len() - last_bit_width;
+    let missed_edge = samples[split];
+
+    let mut poll = PollPhy::default();
+    let mut decoded = None;
+    let jittered = samples[..split]
+        .iter()
+        .copied()
+        .chain(std::iter::once(missed_edge))
+        .chain(samples[split..].iter().copied());
+    for s in jittered {
+        if let Some(m) = poll.push(s) {
+            decoded = Some(m);
+        }
+    }
+
+    assert_eq!(decoded, Some(message));
+}
+
+#[test]
+fn test_push_arms_retransmit_for_echoed_classes() {
+    // sync clear, first control bit clear -> `DataOrEnd`, which is held
+    let held = Message::new(0b000_00000001);
+    assert_eq!(held.class(), MessageClass::DataOrEnd);
+    let mut poll = PollPhy::default();
+    for s in PhyBitEncoder::new(held, DEFAULT_SAMPLES_PER_SYMBOL) {
+        poll.push(s);
+    }
+    assert_eq!(poll.out_signals(), None);
+
+    // sync clear, first control bit set -> `Command`, which must echo
+    let echoed = Message::new(0b010_10010000);
+    assert_eq!(echoed.class(), MessageClass::Command);
+    let mut poll = PollPhy::default();
+    for s in PhyBitEncoder::new(echoed, DEFAULT_SAMPLES_PER_SYMBOL) {
+        poll.push(s);
+    }
+    assert!(poll.out_signals().is_some());
+}
+
+#[test]
+fn test_rfc_and_unlisten_classify_as_ready_and_echo() {
+    // RFC and Unlisten both set `sync` with the first control bit clear,
+    // i.e. `Ready`, derived straight from `control()`'s top two bits (see
+    // `Message::class`) rather than a `MessageType`-name allowlist, so
+    // both echo like any other non-`DataOrEnd` frame.
+    for &ty_message in &[
+        Message::new(0b100_10010000), // ReadyForCommand
+        Message::new(0b100_00111111), // Unlisten
+    ] {
+        assert_eq!(ty_message.class(), MessageClass::Ready);
+        let mut poll = PollPhy::default();
+        for s in PhyBitEncoder::new(ty_message, DEFAULT_SAMPLES_PER_SYMBOL) {
+            poll.push(s);
+        }
+        assert!(poll.out_signals().is_some());
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum MessageClass {
+    /// "DOE"
+    DataOrEnd,
+
+    /// "CMD"
+    Command,
+
+    /// "RDY"
+    Ready,
+
+    /// "IDY"
+    Identify,
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum MessageType {
+    /// "RFC"
+    ///
+    /// `100_10010000`
+    ReadyForCommand,
+
+    /// Sent by a `controller`/`master`.
+    /// Causes any previously active listener to become inactive
+    ///
+    /// `100_00111111`
+    Unlisten,
+
+    /// `101_01100000`
+    SendDataReady,
+
+    /// "SOT": marks the start of a data transfer
+    ///
+    /// raw encoding not yet catalogued here
+    StartOfText,
+
+    /// "IFC": controller-issued interface clear
+    ///
+    /// raw encoding not yet catalogued here
+    InterfaceClear,
+}
+
+impl MessageType {
+    /// Recognize one of the known fixed-encoding message types, if
+    /// `message` matches one exactly.
+    pub fn from_message(message: Message) -> Option<Self> {
+        match (message.control(), message.data()) {
+            (0b100, 0b1001_0000) => Some(Self::ReadyForCommand),
+            (0b100, 0b0011_1111) => Some(Self::Unlisten),
+            (0b101, 0b0110_0000) => Some(Self::SendDataReady),
+            _ => None,
+        }
+    }
+}
+
+
+/// 11-bits of on-bus data
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct Message {
+    /// 1: sync
+    /// 2: control
+    /// 8: data
+    raw: u16,
+}
+
+impl Message {
+    /// Build a message from its raw 11-bit on-bus encoding (`raw`'s
+    /// layout is as documented on the `raw` field; higher bits are
+    /// ignored).
+    pub fn new(raw: u16) -> Self {
+        Message { raw: raw & 0x7ff }
+    }
+
+    /// Defines the major type/class of the message (`MessageClass`)
+    ///
+    /// Includes the `sync` bit (0b100)
+    pub fn control(&self) -> u8 {
+        ((self.raw & (0b111 << 8)) >> 8) as u8
+    }
+
+    /// payload of a message, meaning determined by `major()`.
+    /// remaining 8 bits
+    pub fn data(&self) -> u8 {
+        self.raw as u8
+    }
+
+    /// major type/class of the message, derived from `control()`'s top
+    /// two bits: the `sync` bit and the first control bit. The remaining
+    /// (lowest) control bit selects between the two frames within that
+    /// class (e.g. `Data` vs `End`, or which handshake a `Ready` frame
+    /// is — see `MessageType::from_message`), the same role `data()`'s
+    /// nibbles play in further selecting within a class.
+    ///
+    /// Deriving class from only the two low control bits (dropping
+    /// `sync`) would alias `ReadyForCommand`/`Unlisten`/`SendDataReady`
+    /// (`sync` set) onto whatever other class happens to share their low
+    /// bit, silently misrouting them between echo and hold.
+    pub fn class(&self) -> MessageClass {
+        match self.control() >> 1 {
+            0b00 => MessageClass::DataOrEnd,
+            0b01 => MessageClass::Command,
+            0b10 => MessageClass::Ready,
+            0b11 => MessageClass::Identify,
+            _ => unreachable!(),
+        }
+    }
+}
\ No newline at end of file
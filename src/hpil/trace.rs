@@ -0,0 +1,223 @@
+//! Timestamped capture of decoded messages for offline analysis.
+//!
+//! Modeled on the ring-buffer trace used by RFID/ISO14443 sniffing
+//! tools: a fixed-capacity, no-alloc log of every frame a `PollPhy`
+//! finalizes, tagged with the sample position it started at and which
+//! end of the loop it came from, so a capture taken while bridging
+//! `daisy_in`/`daisy_out` can distinguish controller-originated from
+//! device-originated frames and be correlated against real hardware.
+
+use super::{FrameErrors, Message, PhySample, PollPhy};
+
+/// Which side of a bridged loop a captured frame was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopSide {
+    /// the frame arrived from upstream (the controller, or a device
+    /// closer to it in the chain)
+    Controller,
+    /// the frame arrived from downstream (the device being bridged)
+    Device,
+}
+
+/// One recorded frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// `PhyBitDecoder::sample_count()` at the point this frame's leading
+    /// sync bit started
+    pub start_sample: u64,
+    /// the decoded message
+    pub message: Message,
+    /// which end of a bridged loop produced this frame
+    pub side: LoopSide,
+    /// framing problems noticed while resolving it
+    pub errors: FrameErrors,
+}
+
+/// A fixed-capacity ring buffer of `TraceEntry`, overwriting the oldest
+/// entry once full.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace<const N: usize> {
+    entries: [Option<TraceEntry>; N],
+    // index the next entry will be written to
+    next: usize,
+    len: usize,
+}
+
+impl<const N: usize> Default for Trace<N> {
+    fn default() -> Self {
+        Trace {
+            entries: [None; N],
+            next: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<const N: usize> Trace<N> {
+    pub fn push(&mut self, entry: TraceEntry) {
+        self.entries[self.next] = Some(entry);
+        self.next = (self.next + 1) % N;
+        if self.len < N {
+            self.len += 1;
+        }
+    }
+
+    /// number of recorded entries (`<= N`)
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// iterate recorded entries oldest to newest
+    pub fn iter(&self) -> TraceIter<'_, N> {
+        TraceIter { trace: self, offs: 0 }
+    }
+}
+
+/// Iterates a `Trace`'s entries oldest to newest; see `Trace::iter`.
+pub struct TraceIter<'a, const N: usize> {
+    trace: &'a Trace<N>,
+    offs: usize,
+}
+
+impl<'a, const N: usize> Iterator for TraceIter<'a, N> {
+    type Item = TraceEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offs == self.trace.len {
+            return None;
+        }
+
+        // the oldest entry sits right where the next write will land once
+        // the buffer has wrapped; otherwise it's simply at index 0
+        let start = if self.trace.len == N { self.trace.next } else { 0 };
+        let idx = (start + self.offs) % N;
+        self.offs += 1;
+        self.trace.entries[idx]
+    }
+}
+
+/// Wraps a `PollPhy`, recording every message it finalizes into a
+/// fixed-capacity `Trace` so a full loop transaction log can be dumped
+/// after capture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sniffer<const N: usize> {
+    phy: PollPhy,
+    side: LoopSide,
+    // the in-progress frame's leading sync bit's `BitEvent::start_sample`,
+    // if its sync bit has resolved
+    sync_start: Option<u64>,
+    trace: Trace<N>,
+}
+
+impl<const N: usize> Sniffer<N> {
+    pub fn new(side: LoopSide) -> Self {
+        Sniffer {
+            phy: PollPhy::default(),
+            side,
+            sync_start: None,
+            trace: Trace::default(),
+        }
+    }
+
+    /// Feed one polled line-level sample through the phy, recording a
+    /// `TraceEntry` for every message that resolves.
+    pub fn push(&mut self, sample: PhySample) -> Option<Message> {
+        let (message, errors, bit_event) = self.phy.push_traced(sample);
+
+        if let Some(event) = bit_event {
+            if event.bit_offs == 0 {
+                self.sync_start = Some(event.start_sample);
+            }
+        }
+
+        if let Some(message) = message {
+            self.trace.push(TraceEntry {
+                start_sample: self.sync_start.unwrap_or_else(|| self.phy.sample_count()),
+                message,
+                side: self.side,
+                errors,
+            });
+            self.sync_start = None;
+        }
+
+        message
+    }
+
+    /// Pull the next `PhySample` of an in-progress retransmission, if
+    /// any; see `PollPhy::out_signals`.
+    pub fn out_signals(&mut self) -> Option<PhySample> {
+        self.phy.out_signals()
+    }
+
+    /// the captured frames so far, oldest to newest
+    pub fn trace(&self) -> &Trace<N> {
+        &self.trace
+    }
+}
+
+#[test]
+fn test_trace_ring_buffer_wraps() {
+    let mut trace: Trace<2> = Trace::default();
+    let entry = |start_sample| TraceEntry {
+        start_sample,
+        message: Message::new(0),
+        side: LoopSide::Controller,
+        errors: FrameErrors::default(),
+    };
+
+    trace.push(entry(1));
+    trace.push(entry(2));
+    trace.push(entry(3));
+
+    assert_eq!(trace.len(), 2);
+    let starts: Vec<u64> = trace.iter().map(|e| e.start_sample).collect();
+    assert_eq!(starts, vec![2, 3]);
+}
+
+#[test]
+fn test_sniffer_records_messages_with_start_sample() {
+    use super::{PhyBitEncoder, DEFAULT_SAMPLES_PER_SYMBOL};
+
+    let message = Message::new(0b100_10010000);
+    let mut sniffer: Sniffer<4> = Sniffer::new(LoopSide::Controller);
+
+    for s in PhyBitEncoder::new(message, DEFAULT_SAMPLES_PER_SYMBOL) {
+        sniffer.push(s);
+    }
+
+    let entries: Vec<TraceEntry> = sniffer.trace().iter().collect();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].message, message);
+    assert_eq!(entries[0].start_sample, 0);
+    assert_eq!(entries[0].side, LoopSide::Controller);
+    assert!(!entries[0].errors.any());
+}
+
+#[test]
+fn test_sniffer_start_sample_survives_an_idle_gap_before_the_frame() {
+    // a frame preceded by idle line samples: `start_sample` must
+    // name the sample its own sync bit began at, not the earlier point
+    // the gap-then-search began.
+    use super::{PhyBitEncoder, DEFAULT_SAMPLES_PER_SYMBOL};
+
+    let message = Message::new(0b100_10010000);
+    let mut sniffer: Sniffer<4> = Sniffer::new(LoopSide::Controller);
+
+    let gap = 1;
+    for _ in 0..gap {
+        sniffer.push(PhySample::Zero);
+    }
+
+    for s in PhyBitEncoder::new(message, DEFAULT_SAMPLES_PER_SYMBOL) {
+        sniffer.push(s);
+    }
+
+    let entries: Vec<TraceEntry> = sniffer.trace().iter().collect();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].message, message);
+    assert_eq!(entries[0].start_sample, gap);
+}
@@ -23,7 +23,7 @@ impl Hp41Module {
 */
 
 pub mod hpil;
-//pub mod hp71bus;
+pub mod hp71bus;
 
 fn main() {
     println!("Hello, world!");